@@ -0,0 +1,424 @@
+//! ELF64 parsing, allocating the parsed program/section header tables
+//! (and resolved section names) into an [`Arena`] rather than the global
+//! allocator.
+
+use std::mem::size_of;
+
+use arena::{Arena, Pod};
+
+pub const MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+
+/// `EI_CLASS` value for 64-bit objects; the only class this parser
+/// understands.
+pub const ELFCLASS64: u8 = 2;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct Elf64Header {
+    /// Magic number and other info
+    pub ident: [u8; 16],
+
+    /// Object file type
+    ///
+    /// todo: change to enum
+    pub r#type: u16,
+
+    /// Architecture
+    pub machine: u16,
+
+    /// Object file version
+    pub version: u32,
+
+    /// Entry point virtual address
+    pub entry: u64,
+
+    /// Program header table file offset
+    ///
+    /// Offset from start of file (including this header).
+    pub phoff: u64,
+
+    /// Section header table file offset
+    ///
+    /// Offset from start of file (including this header).
+    pub shoff: u64,
+
+    /// Processor-specific flags
+    pub flags: u32,
+
+    /// ELF header size in bytes
+    pub ehsize: u16,
+
+    /// Program header table entry size
+    pub phentsize: u16,
+
+    /// Program header table entry count
+    pub phnum: u16,
+
+    /// Section header table entry size
+    pub shentsize: u16,
+
+    /// Section header table entry count
+    pub shnum: u16,
+
+    /// Section header string table index
+    pub shstrndx: u16,
+}
+
+// `Elf64Header` is `repr(C)`, has no padding (every field lines up on its
+// own alignment), and any bit pattern is a valid header, so it's fine to
+// view directly over file bytes via `Arena::view`.
+unsafe impl Pod for Elf64Header {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramHeader {
+    pub r#type: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub paddr: u64,
+    pub filesz: u64,
+    pub memsz: u64,
+    pub align: u64,
+}
+
+unsafe impl Pod for ProgramHeader {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SectionHeader {
+    pub name: u32,
+    pub r#type: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+    pub addralign: u64,
+    pub entsize: u64,
+}
+
+unsafe impl Pod for SectionHeader {}
+
+/// A section header with its name already resolved through the section
+/// header string table.
+#[derive(Debug)]
+pub struct Section<'a> {
+    pub header: SectionHeader,
+    pub name: &'a str,
+}
+
+#[derive(Debug)]
+pub enum ElfError {
+    /// `ident[0..4]` wasn't `\x7fELF`
+    BadMagic([u8; 4]),
+
+    /// `ident[4]` (`EI_CLASS`) wasn't `ELFCLASS64`
+    UnsupportedClass(u8),
+
+    /// a table entry (or the table itself) fell outside the file
+    TruncatedAt { table: &'static str, offset: u64 },
+
+    /// an entry/header size field didn't match the size we expect to read
+    HeaderSizeMismatch { expected: u16, got: u16 },
+
+    /// a section's `name` offset fell outside the section header string
+    /// table, or wasn't valid UTF-8
+    BadSectionName { section: usize, name_offset: u32 },
+}
+
+/// A parsed ELF object file, with its tables allocated in `arena`.
+///
+/// `'a` is how long the parsed data stays borrowed; `'arena` is the
+/// arena's own (otherwise unconstrained) lifetime parameter.
+pub struct Elf<'a, 'arena> {
+    pub header: &'a Elf64Header,
+    pub program_headers: Vec<ProgramHeader, &'a Arena<'arena>>,
+    pub sections: Vec<Section<'a>, &'a Arena<'arena>>,
+}
+
+impl<'a, 'arena> Elf<'a, 'arena> {
+    pub fn parse(arena: &'a Arena<'arena>, bytes: &[u8]) -> Result<Elf<'a, 'arena>, ElfError> {
+        let header: &Elf64Header = arena
+            .view(bytes)
+            .ok_or(ElfError::TruncatedAt { table: "ELF header", offset: 0 })?;
+
+        if !header.ident.starts_with(&MAGIC) {
+            return Err(ElfError::BadMagic([
+                header.ident[0], header.ident[1], header.ident[2], header.ident[3],
+            ]));
+        }
+
+        if header.ident[4] != ELFCLASS64 {
+            return Err(ElfError::UnsupportedClass(header.ident[4]));
+        }
+
+        if header.ehsize as usize != size_of::<Elf64Header>() {
+            return Err(ElfError::HeaderSizeMismatch {
+                expected: size_of::<Elf64Header>() as u16,
+                got: header.ehsize,
+            });
+        }
+
+        let program_headers = read_table::<ProgramHeader>(
+            arena, bytes, "program header table", header.phoff, header.phnum, header.phentsize)?;
+
+        let section_headers = read_table::<SectionHeader>(
+            arena, bytes, "section header table", header.shoff, header.shnum, header.shentsize)?;
+
+        let shstrtab = section_headers.get(header.shstrndx as usize).ok_or(
+            ElfError::TruncatedAt { table: "section header string table", offset: header.shstrndx as u64 })?;
+
+        let strtab = bytes
+            .get(shstrtab.offset as usize..)
+            .and_then(|b| b.get(..shstrtab.size as usize))
+            .ok_or(ElfError::TruncatedAt { table: "section header string table", offset: shstrtab.offset })?;
+
+        let mut sections = Vec::with_capacity_in(section_headers.len(), arena);
+        for (i, sh) in section_headers.iter().enumerate() {
+            let name = read_str(strtab, sh.name as usize)
+                .ok_or(ElfError::BadSectionName { section: i, name_offset: sh.name })?;
+            sections.push(Section { header: *sh, name: alloc_str(arena, name) });
+        }
+
+        Ok(Elf { header, program_headers, sections })
+    }
+}
+
+/// Reads `count` entries of `T` starting at `off` in `bytes`, bounds- and
+/// size-checked, into a `Vec` allocated in `arena`.
+fn read_table<'a, 'arena, T: Pod + Copy>(
+    arena: &'a Arena<'arena>,
+    bytes: &[u8],
+    table: &'static str,
+    off: u64,
+    count: u16,
+    entsize: u16,
+) -> Result<Vec<T, &'a Arena<'arena>>, ElfError> {
+    if entsize as usize != size_of::<T>() {
+        return Err(ElfError::HeaderSizeMismatch { expected: size_of::<T>() as u16, got: entsize });
+    }
+
+    let span = (count as u64)
+        .checked_mul(entsize as u64)
+        .ok_or(ElfError::TruncatedAt { table, offset: off })?;
+    let end = off.checked_add(span).ok_or(ElfError::TruncatedAt { table, offset: off })?;
+    if end > bytes.len() as u64 {
+        return Err(ElfError::TruncatedAt { table, offset: off });
+    }
+
+    let mut entries = Vec::with_capacity_in(count as usize, arena);
+    for i in 0..count as u64 {
+        let entry_off = off + i * entsize as u64;
+        let entry: &T = arena
+            .read_at(bytes, entry_off as usize)
+            .ok_or(ElfError::TruncatedAt { table, offset: entry_off })?;
+        entries.push(*entry);
+    }
+
+    Ok(entries)
+}
+
+/// Reads a NUL-terminated string starting at `off` in `bytes`.
+fn read_str(bytes: &[u8], off: usize) -> Option<&str> {
+    let tail = bytes.get(off..)?;
+    let len = tail.iter().position(|&b| b == 0)?;
+    std::str::from_utf8(&tail[..len]).ok()
+}
+
+/// Copies `s` into `arena` so it can live as long as the `Elf` it's part
+/// of, independent of the original file bytes.
+fn alloc_str<'a, 'arena>(arena: &'a Arena<'arena>, s: &str) -> &'a str {
+    let mut buf: Vec<u8, &Arena> = Vec::with_capacity_in(s.len(), arena);
+    buf.extend_from_slice(s.as_bytes());
+    unsafe { std::str::from_utf8_unchecked(buf.leak()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arena::KB;
+
+    fn raw<T>(v: &T) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(v as *const T as *const u8, size_of::<T>()) }
+    }
+
+    fn valid_header() -> Elf64Header {
+        let mut ident = [0u8; 16];
+        ident[..4].copy_from_slice(&MAGIC);
+        ident[4] = ELFCLASS64;
+
+        Elf64Header {
+            ident,
+            r#type: 2,
+            machine: 0x3e,
+            version: 1,
+            entry: 0,
+            phoff: size_of::<Elf64Header>() as u64,
+            shoff: size_of::<Elf64Header>() as u64 + size_of::<ProgramHeader>() as u64,
+            flags: 0,
+            ehsize: size_of::<Elf64Header>() as u16,
+            phentsize: size_of::<ProgramHeader>() as u16,
+            phnum: 1,
+            shentsize: size_of::<SectionHeader>() as u16,
+            shnum: 2,
+            shstrndx: 1,
+        }
+    }
+
+    fn valid_program_header() -> ProgramHeader {
+        ProgramHeader { r#type: 1, flags: 0, offset: 0, vaddr: 0, paddr: 0, filesz: 0, memsz: 0, align: 0 }
+    }
+
+    fn section_header(name: u32, offset: u64, size: u64) -> SectionHeader {
+        SectionHeader {
+            name, r#type: 0, flags: 0, addr: 0, offset, size, link: 0, info: 0, addralign: 0, entsize: 0,
+        }
+    }
+
+    // header, one program header, a null section and a `.shstrtab` section
+    // whose string table is `"\0.shstrtab\0"` (the leading NUL is the empty
+    // name the null section resolves to).
+    const STRTAB: &[u8] = b"\0.shstrtab\0";
+
+    fn valid_file() -> Vec<u8> {
+        let strtab_off =
+            size_of::<Elf64Header>() + size_of::<ProgramHeader>() + 2 * size_of::<SectionHeader>();
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(raw(&valid_header()));
+        bytes.extend_from_slice(raw(&valid_program_header()));
+        bytes.extend_from_slice(raw(&section_header(0, 0, 0)));
+        bytes.extend_from_slice(raw(&section_header(1, strtab_off as u64, STRTAB.len() as u64)));
+        bytes.extend_from_slice(STRTAB);
+        bytes
+    }
+
+    // byte offsets of the `Elf64Header` fields touched by the tests below
+    const EHSIZE_OFF: usize = 52;
+    const PHENTSIZE_OFF: usize = 54;
+    const PHNUM_OFF: usize = 56;
+    const SHNUM_OFF: usize = 60;
+    const SHSTRNDX_OFF: usize = 62;
+
+    #[test]
+    fn parses_a_well_formed_file() {
+        let bytes = valid_file();
+
+        Arena::with(4 * KB, |arena| {
+            let elf = Elf::parse(arena, &bytes).unwrap();
+            assert_eq!(elf.program_headers.len(), 1);
+            assert_eq!(elf.sections.len(), 2);
+            assert_eq!(elf.sections[0].name, "");
+            assert_eq!(elf.sections[1].name, ".shstrtab");
+        });
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = valid_file();
+        bytes[0] = 0;
+
+        Arena::with(4 * KB, |arena| {
+            assert!(matches!(Elf::parse(arena, &bytes), Err(ElfError::BadMagic(_))));
+        });
+    }
+
+    #[test]
+    fn rejects_unsupported_class() {
+        let mut bytes = valid_file();
+        bytes[4] = 1; // ELFCLASS32
+
+        Arena::with(4 * KB, |arena| {
+            assert!(matches!(Elf::parse(arena, &bytes), Err(ElfError::UnsupportedClass(1))));
+        });
+    }
+
+    #[test]
+    fn rejects_ehsize_mismatch() {
+        let mut bytes = valid_file();
+        bytes[EHSIZE_OFF..EHSIZE_OFF + 2].copy_from_slice(&10u16.to_le_bytes());
+
+        Arena::with(4 * KB, |arena| {
+            assert!(matches!(Elf::parse(arena, &bytes), Err(ElfError::HeaderSizeMismatch { .. })));
+        });
+    }
+
+    #[test]
+    fn rejects_entsize_mismatch() {
+        let mut bytes = valid_file();
+        bytes[PHENTSIZE_OFF..PHENTSIZE_OFF + 2].copy_from_slice(&8u16.to_le_bytes());
+
+        Arena::with(4 * KB, |arena| {
+            assert!(matches!(Elf::parse(arena, &bytes), Err(ElfError::HeaderSizeMismatch { .. })));
+        });
+    }
+
+    #[test]
+    fn rejects_truncated_program_header_table() {
+        let mut bytes = valid_file();
+        bytes[PHNUM_OFF..PHNUM_OFF + 2].copy_from_slice(&1000u16.to_le_bytes());
+
+        Arena::with(4 * KB, |arena| {
+            assert!(matches!(
+                Elf::parse(arena, &bytes),
+                Err(ElfError::TruncatedAt { table: "program header table", .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn rejects_truncated_section_header_table() {
+        let mut bytes = valid_file();
+        bytes[SHNUM_OFF..SHNUM_OFF + 2].copy_from_slice(&1000u16.to_le_bytes());
+
+        Arena::with(4 * KB, |arena| {
+            assert!(matches!(
+                Elf::parse(arena, &bytes),
+                Err(ElfError::TruncatedAt { table: "section header table", .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn rejects_out_of_range_shstrndx() {
+        let mut bytes = valid_file();
+        bytes[SHSTRNDX_OFF..SHSTRNDX_OFF + 2].copy_from_slice(&5u16.to_le_bytes());
+
+        Arena::with(4 * KB, |arena| {
+            assert!(matches!(
+                Elf::parse(arena, &bytes),
+                Err(ElfError::TruncatedAt { table: "section header string table", .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn rejects_section_name_missing_terminator() {
+        let mut bytes = valid_file();
+        let last = bytes.len() - 1;
+        bytes[last] = b'X'; // drop the string table's trailing NUL
+
+        Arena::with(4 * KB, |arena| {
+            assert!(matches!(
+                Elf::parse(arena, &bytes),
+                Err(ElfError::BadSectionName { section: 1, .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn rejects_non_utf8_section_name() {
+        let mut bytes = valid_file();
+        let name_start = bytes.len() - STRTAB.len() + 1; // just past the leading NUL
+        bytes[name_start] = 0xFF; // not valid UTF-8 on its own, keeps the terminator
+
+        Arena::with(4 * KB, |arena| {
+            assert!(matches!(
+                Elf::parse(arena, &bytes),
+                Err(ElfError::BadSectionName { section: 1, .. })
+            ));
+        });
+    }
+}
@@ -5,6 +5,18 @@ use std::ptr::NonNull;
 use std::ptr::addr_of;
 use std::cell::UnsafeCell;
 
+mod size;
+pub use size::{Align, AlignError, Size};
+
+mod pod;
+pub use pod::Pod;
+
+mod snapshot;
+pub use snapshot::{Header, SnapshotError};
+
+mod reference;
+pub use reference::Ref;
+
 pub const KB: usize = 1024;
 pub const MB: usize = 1024 * KB;
 
@@ -15,10 +27,16 @@ pub struct Arena<'a> {
     data: UnsafeCell<ArenaData>
 }
 
+/// A single, independently-allocated backing buffer.
+///
+/// A `Chunk`'s storage is never moved or resized after creation: once
+/// handed out, a pointer into a chunk stays valid for as long as the
+/// `Arena` (and therefore the chunk) is alive, even as later chunks are
+/// appended.
 #[derive(Debug)]
-pub(crate) struct ArenaData {
-    /// underlying storage for the arena
-    pub(crate) storage: Vec<u8>,
+pub(crate) struct Chunk {
+    /// underlying storage for this chunk
+    pub(crate) storage: Box<[u8]>,
 
     /// base address of the storage
     pub(crate) base_address: usize,
@@ -27,19 +45,92 @@ pub(crate) struct ArenaData {
     pub(crate) offset: usize,
 }
 
-
-impl<'a> Arena<'a> {
-    pub fn with<R>(bytes: usize, k: impl for <'b> FnOnce(&Arena<'b>) -> R) -> R {
-
-        // TODO: could use uninitialised memory in non-debug scenario?
-        let storage = vec![0u8; bytes];
+impl Chunk {
+    fn new(bytes: usize) -> Chunk {
+        let storage = vec![0u8; bytes].into_boxed_slice();
         let base_address = addr_of!(storage[0]) as usize;
-        let offset = 0;
 
-        let data = UnsafeCell::new(ArenaData {
+        Chunk {
             storage,
             base_address,
-            offset,
+            offset: 0,
+        }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.storage.len() - self.offset
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ArenaData {
+    /// chunks backing this arena, in allocation order
+    ///
+    /// chunks after `current` can exist after a `reset_to`: they were
+    /// allocated once and are kept around so a later `grow` can reuse them
+    /// instead of asking the OS for more memory.
+    pub(crate) chunks: Vec<Chunk>,
+
+    /// index of the chunk new allocations try first
+    pub(crate) current: usize,
+
+    /// size used for chunks grown automatically, unless a larger size is
+    /// required to fit a single allocation
+    pub(crate) default_chunk_bytes: usize,
+}
+
+impl ArenaData {
+    fn current_chunk(&mut self) -> &mut Chunk {
+        &mut self.chunks[self.current]
+    }
+
+    /// moves onto a chunk able to hold `at_least` bytes, reusing a
+    /// chunk left over from a previous `reset_to` if it's large enough,
+    /// otherwise dropping it and allocating a fresh one.
+    fn grow(&mut self, at_least: usize) {
+        if let Some(next) = self.chunks.get(self.current + 1) {
+            if next.storage.len() >= at_least {
+                self.current += 1;
+                return;
+            }
+        }
+
+        self.chunks.truncate(self.current + 1);
+        let bytes = std::cmp::max(self.default_chunk_bytes, at_least);
+        self.chunks.push(Chunk::new(bytes));
+        self.current += 1;
+    }
+
+    pub(crate) fn total_allocated(&self) -> usize {
+        self.chunks.iter().map(|c| c.storage.len()).sum()
+    }
+
+    pub(crate) fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+/// A saved `(chunk, offset)` position in an [`Arena`], produced by
+/// [`Arena::mark`] and consumed by [`Arena::reset_to`].
+#[derive(Debug, Clone, Copy)]
+pub struct Marker {
+    chunk_index: usize,
+    offset: usize,
+}
+
+/// Byte pattern used to overwrite reclaimed memory in debug builds, so
+/// that a use-after-reset shows up as obviously garbage data rather than
+/// silently-still-correct leftover bytes.
+#[cfg(debug_assertions)]
+const POISON: u8 = 0xDD;
+
+
+impl<'a> Arena<'a> {
+    pub fn with<R>(bytes: usize, k: impl for <'b> FnOnce(&Arena<'b>) -> R) -> R {
+        let data = UnsafeCell::new(ArenaData {
+            chunks: vec![Chunk::new(bytes)],
+            current: 0,
+            default_chunk_bytes: bytes,
         });
 
         k(&Arena {
@@ -51,40 +142,119 @@ impl<'a> Arena<'a> {
     pub(crate) unsafe fn get_data(&self) -> &mut ArenaData {
         &mut *self.data.get()
     }
+
+    /// Ensures at least `bytes` are available without growing mid-allocation,
+    /// by eagerly moving to another chunk if the current one can't fit them
+    /// (reusing one left over from a `reset_to` if it's large enough,
+    /// otherwise appending a new one).
+    pub fn reserve(&self, bytes: usize) {
+        let data = unsafe { self.get_data() };
+
+        if data.current_chunk().remaining() < bytes {
+            data.grow(bytes);
+        }
+    }
+
+    /// Total bytes backing this arena across all chunks (used and unused).
+    pub fn total_allocated(&self) -> usize {
+        unsafe { self.get_data() }.total_allocated()
+    }
+
+    /// Number of chunks backing this arena.
+    pub fn chunk_count(&self) -> usize {
+        unsafe { self.get_data() }.chunk_count()
+    }
+
+    /// Captures the arena's current allocation position.
+    ///
+    /// Pair with [`reset_to`](Arena::reset_to) to cheaply reuse the memory
+    /// allocated since the mark, e.g. between parsing successive object
+    /// files into the same arena.
+    pub fn mark(&self) -> Marker {
+        let data = unsafe { self.get_data() };
+
+        Marker {
+            chunk_index: data.current,
+            offset: data.chunks[data.current].offset,
+        }
+    }
+
+    /// Rewinds the arena to a previously captured [`Marker`], logically
+    /// freeing everything allocated since then so later allocations reuse
+    /// the space.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that no reference into the reclaimed
+    /// region (anything allocated after `m` was captured) outlives this
+    /// call. In debug builds the reclaimed bytes are overwritten with a
+    /// poison pattern so a lingering use shows up quickly.
+    pub unsafe fn reset_to(&self, m: Marker) {
+        let data = unsafe { self.get_data() };
+
+        #[cfg(debug_assertions)]
+        data.chunks[m.chunk_index].storage[m.offset..].fill(POISON);
+        data.chunks[m.chunk_index].offset = m.offset;
+
+        for chunk in &mut data.chunks[m.chunk_index + 1..] {
+            #[cfg(debug_assertions)]
+            chunk.storage.fill(POISON);
+            chunk.offset = 0;
+        }
+
+        data.current = m.chunk_index;
+    }
 }
 
 unsafe impl<'a> Allocator for &Arena<'a> {
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
         let data = unsafe { self.get_data() };
 
-        // get the address of the base
-        let base = data.base_address;
-        let alignment = layout.align();
-        // align the base address
-        let aligned = (base + (alignment - 1)) & (!alignment + 1);
-        // calculate the amount we need to add to base to have a correctly aligned address
-        let alignment = aligned - base;
+        fn try_allocate(chunk: &mut Chunk, layout: Layout) -> Option<NonNull<[u8]>> {
+            // layout.align() is always a power of two, guaranteed by Layout itself
+            let align = Align::from_bytes(layout.align()).ok()?;
+            let size = Size::from_bytes(layout.size());
+
+            let current_offset = chunk.offset;
+
+            // align the *current* address (base + offset), not just the base:
+            // otherwise the padding only happens to be correct when the offset
+            // is already a multiple of `align`.
+            let base = Size::from_bytes(chunk.base_address);
+            let current = base.checked_add(Size::from_bytes(current_offset))?;
+            let aligned = current.align_to(align)?;
+            let padding = aligned.checked_sub(current)?;
 
-        // size of the allocation adjusted for alignment at the front
-        let total_size = alignment + layout.size();
+            // size of the allocation adjusted for alignment at the front
+            let total_size = padding.checked_add(size)?;
 
-        let current_offset: usize = data.offset;
+            let used = Size::from_bytes(current_offset).checked_add(total_size)?;
 
-        // space leftover in storage
-        let space = data.storage.len() - current_offset;
+            // check if there is enough space in the chunk
+            if used.bytes() > chunk.storage.len() {
+                return None
+            }
 
-        // check if there is enough space in the arena
-        if total_size > space {
-            return Err(AllocError)
+            // update offset
+            chunk.offset += total_size.bytes();
+
+            let start = current_offset + padding.bytes();
+            Some(unsafe {
+                NonNull::new_unchecked(&mut chunk.storage[start..start + size.bytes()])
+            })
         }
 
-        // update offset
-        data.offset += total_size;
+        if let Some(ptr) = try_allocate(data.current_chunk(), layout) {
+            return Ok(ptr)
+        }
 
-        Ok(unsafe {
-            NonNull::new_unchecked(
-                &mut data.storage[current_offset+alignment..layout.size()])
-        })
+        // current chunk couldn't fit it: grow and retry in the fresh chunk
+        let at_least = Size::from_bytes(layout.size())
+            .checked_add(Size::from_bytes(layout.align()))
+            .ok_or(AllocError)?;
+        data.grow(at_least.bytes());
+
+        try_allocate(data.current_chunk(), layout).ok_or(AllocError)
     }
 
     unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {
@@ -105,7 +275,7 @@ mod tests {
             vec.push(1);
             println!(
                 "0x{:x?},\n0x{:x?}",
-                unsafe { arena.get_data() }.base_address,
+                unsafe { arena.get_data() }.current_chunk().base_address,
                 addr_of!(vec[0]) as usize);
 
             println!("{:?}", vec);
@@ -121,8 +291,10 @@ mod tests {
 
             println!("{:?}", res);
 
-            // reserve should fail
-            assert!(matches!(res, Err(_)));
+            // try_reserve should succeed: the arena grows a new chunk instead
+            // of failing the allocation
+            assert!(matches!(res, Ok(_)));
+            assert_eq!(arena.chunk_count(), 2);
         });
     }
 
@@ -149,4 +321,79 @@ mod tests {
             assert!(0 == (addr_of!(*b) as usize & 0xf));
         });
     }
+
+    #[test]
+    fn padding_accounts_for_the_current_offset() {
+        Arena::with(4 * KB, |arena| {
+            // three 1-byte, align(1) allocations leave the chunk offset at 3...
+            let _lead: Box<u8, _> = Box::new_in(0u8, arena);
+            let _lead: Box<u8, _> = Box::new_in(0u8, arena);
+            let _lead: Box<u8, _> = Box::new_in(0u8, arena);
+
+            // ...so the padding in front of this allocation must be computed
+            // from base_address + 3, not from base_address alone, or the
+            // returned pointer won't actually be 8-byte aligned.
+            let aligned: Box<u64, _> = Box::new_in(0u64, arena);
+            assert_eq!(addr_of!(*aligned) as usize % 8, 0);
+        });
+    }
+
+    #[test]
+    fn growth_preserves_existing_pointers() {
+        Arena::with(512, |arena| {
+            let first: Box<[u8; 512], _> = Box::new_in([1u8; 512], arena);
+            let first_ptr = addr_of!(*first) as usize;
+
+            // this allocation doesn't fit in the remaining space of the
+            // first chunk, so the arena must grow a new chunk for it.
+            let second: Box<[u8; 512], _> = Box::new_in([2u8; 512], arena);
+
+            assert_eq!(arena.chunk_count(), 2);
+            // the first allocation's address must still be valid and
+            // untouched after the arena grew.
+            assert_eq!(addr_of!(*first) as usize, first_ptr);
+            assert_eq!(*first, [1u8; 512]);
+            assert_eq!(*second, [2u8; 512]);
+        });
+    }
+
+    #[test]
+    fn reset_to_reuses_space() {
+        Arena::with(4 * KB, |arena| {
+            let m = arena.mark();
+
+            let _first: Box<u32, _> = Box::new_in(1, arena);
+            let first_addr = addr_of!(*_first) as usize;
+            let before = arena.total_allocated();
+
+            unsafe { arena.reset_to(m) };
+
+            let _second: Box<u32, _> = Box::new_in(2, arena);
+
+            // the space allocated since the mark was reused, not grown.
+            assert_eq!(addr_of!(*_second) as usize, first_addr);
+            assert_eq!(arena.total_allocated(), before);
+            assert_eq!(arena.chunk_count(), 1);
+        });
+    }
+
+    #[test]
+    fn reset_to_reuses_trailing_chunk() {
+        Arena::with(512, |arena| {
+            let m = arena.mark();
+
+            // forces growth to a second chunk
+            let _first: Box<[u8; 512], _> = Box::new_in([1u8; 512], arena);
+            let _second: Box<[u8; 512], _> = Box::new_in([2u8; 512], arena);
+            assert_eq!(arena.chunk_count(), 2);
+
+            unsafe { arena.reset_to(m) };
+
+            // re-allocating the same amount should reuse both chunks rather
+            // than allocating new ones.
+            let _third: Box<[u8; 512], _> = Box::new_in([3u8; 512], arena);
+            let _fourth: Box<[u8; 512], _> = Box::new_in([4u8; 512], arena);
+            assert_eq!(arena.chunk_count(), 2);
+        });
+    }
 }
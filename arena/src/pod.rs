@@ -0,0 +1,126 @@
+//! Zero-copy, alignment-checked typed views over byte buffers.
+//!
+//! Casting an under-aligned `&[u8]` directly to `&T` is the classic
+//! undefined-behavior pattern this module exists to avoid: [`Arena::view`]
+//! and [`Arena::read_at`] only ever hand out a reference that's actually
+//! aligned for `T`, falling back to a bump-allocated, correctly aligned
+//! copy when the source bytes aren't.
+
+use std::alloc::{Allocator, Layout};
+use std::mem::{align_of, size_of};
+use std::ptr;
+
+use crate::Arena;
+
+/// Marker for types that can be safely viewed directly over arbitrary
+/// bytes.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` (or a primitive integer, or an array
+/// of such), have no padding bytes, and treat every bit pattern of their
+/// size as a valid value.
+pub unsafe trait Pod {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+impl<'a> Arena<'a> {
+    /// Views the start of `bytes` as a `&T`.
+    ///
+    /// Returns `None` if `bytes` is shorter than `size_of::<T>()`.
+    pub fn view<T: Pod>(&self, bytes: &[u8]) -> Option<&T> {
+        self.read_at(bytes, 0)
+    }
+
+    /// Views `bytes` at offset `off` as a `&T`.
+    ///
+    /// If the source is already aligned for `T` the reference borrows
+    /// straight into `bytes`. Otherwise the bytes are copied into a fresh,
+    /// correctly aligned slot bump-allocated from this arena.
+    ///
+    /// Returns `None` if `off + size_of::<T>()` overflows or is out of
+    /// bounds for `bytes`.
+    pub fn read_at<T: Pod>(&self, bytes: &[u8], off: usize) -> Option<&T> {
+        let size = size_of::<T>();
+        let end = off.checked_add(size)?;
+        let src = bytes.get(off..end)?;
+
+        if (src.as_ptr() as usize) & (align_of::<T>() - 1) == 0 {
+            return Some(unsafe { &*(src.as_ptr() as *const T) });
+        }
+
+        let layout = Layout::new::<T>();
+        let ptr = self.allocate(layout).ok()?.as_ptr() as *mut T;
+
+        unsafe {
+            ptr::copy_nonoverlapping(src.as_ptr(), ptr as *mut u8, size);
+            Some(&*ptr)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Arena, KB};
+
+    #[derive(Debug, PartialEq)]
+    #[repr(C)]
+    struct Pair {
+        a: u32,
+        b: u32,
+    }
+
+    unsafe impl Pod for Pair {}
+
+    // byte buffer whose start is aligned for `Pair`
+    #[repr(C, align(4))]
+    struct Aligned<const N: usize>([u8; N]);
+
+    #[test]
+    fn view_borrows_in_place_when_aligned() {
+        Arena::with(KB, |arena| {
+            let bytes = Aligned([1, 0, 0, 0, 2, 0, 0, 0]);
+            let pair: &Pair = arena.view(&bytes.0).unwrap();
+            assert_eq!(*pair, Pair { a: 1, b: 2 });
+            assert_eq!(pair as *const Pair as usize, bytes.0.as_ptr() as usize);
+        });
+    }
+
+    #[test]
+    fn read_at_copies_when_misaligned() {
+        Arena::with(KB, |arena| {
+            // one leading byte forces the `Pair` view to start off-alignment
+            let bytes = Aligned([0xff, 1, 0, 0, 0, 2, 0, 0, 0]);
+            let pair: &Pair = arena.read_at(&bytes.0, 1).unwrap();
+            assert_eq!(*pair, Pair { a: 1, b: 2 });
+            assert_ne!(pair as *const Pair as usize, bytes.0.as_ptr() as usize + 1);
+        });
+    }
+
+    #[test]
+    fn read_at_rejects_short_buffers() {
+        Arena::with(KB, |arena| {
+            let bytes: [u8; 4] = [1, 0, 0, 0];
+            let pair: Option<&Pair> = arena.read_at(&bytes, 0);
+            assert!(pair.is_none());
+        });
+    }
+
+    #[test]
+    fn read_at_rejects_overflowing_offsets_instead_of_panicking() {
+        Arena::with(KB, |arena| {
+            let bytes: [u8; 4] = [1, 0, 0, 0];
+            let pair: Option<&Pair> = arena.read_at(&bytes, usize::MAX - 2);
+            assert!(pair.is_none());
+        });
+    }
+}
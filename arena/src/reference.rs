@@ -0,0 +1,111 @@
+//! An offset-based alternative to `Box::new_in` for data that needs to
+//! survive a [`save`](Arena::save)/[`load`](Arena::load) round trip.
+
+use std::alloc::{AllocError, Allocator, Layout};
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+use crate::Arena;
+
+/// A reference into an arena's first chunk, stored as a byte offset from
+/// the chunk's base address rather than a raw pointer.
+///
+/// Unlike a `Box<T, &Arena>`, a `Ref<T>` stays meaningful after the arena
+/// (and therefore its base address) has moved, e.g. across a save/load
+/// round trip: [`get`](Ref::get) always resolves against the *current*
+/// base address of whatever arena it's handed.
+#[derive(Debug, Clone, Copy)]
+pub struct Ref<T> {
+    offset: usize,
+    _marker: PhantomData<*const T>,
+}
+
+impl<T> Ref<T> {
+    /// Resolves this reference against `arena`'s current base address.
+    ///
+    /// Returns `None` if `offset + size_of::<T>()` falls outside `arena`'s
+    /// first chunk, e.g. because this `Ref` was produced by a different,
+    /// larger arena.
+    pub fn get<'b>(&self, arena: &'b Arena) -> Option<&'b T> {
+        let data = unsafe { arena.get_data() };
+        let chunk = &data.chunks[0];
+
+        let end = self.offset.checked_add(size_of::<T>())?;
+        if end > chunk.storage.len() {
+            return None;
+        }
+
+        let base = chunk.base_address;
+        Some(unsafe { &*((base + self.offset) as *const T) })
+    }
+}
+
+impl<'a> Arena<'a> {
+    /// Allocates `value` in this arena's first chunk and returns a
+    /// position-independent [`Ref`] to it.
+    ///
+    /// Fails rather than growing a second chunk: a `Ref` only resolves
+    /// against chunk 0's base address, so `reserve` enough space up front
+    /// if you intend to `alloc_ref` into this arena.
+    pub fn alloc_ref<T>(&self, value: T) -> Result<Ref<T>, AllocError> {
+        let layout = Layout::new::<T>();
+        let data = unsafe { self.get_data() };
+
+        if data.chunk_count() != 1 {
+            return Err(AllocError);
+        }
+
+        if data.chunks[0].remaining() < layout.size() + layout.align() {
+            return Err(AllocError);
+        }
+
+        let ptr = Allocator::allocate(&self, layout)?.as_ptr() as *mut u8 as *mut T;
+        unsafe { ptr.write(value) };
+
+        let base = data.chunks[0].base_address;
+        let offset = ptr as usize - base;
+
+        Ok(Ref { offset, _marker: PhantomData })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arena, Ref, KB};
+
+    #[test]
+    fn get_resolves_against_the_handed_arena() {
+        Arena::with(KB, |arena| {
+            let r = arena.alloc_ref(42u32).unwrap();
+            assert_eq!(*r.get(arena).unwrap(), 42);
+        });
+    }
+
+    #[test]
+    fn fails_once_the_arena_has_grown_a_second_chunk() {
+        Arena::with(16, |arena| {
+            // forces growth to a second chunk
+            let _big: Box<[u8; 64], _> = Box::new_in([0u8; 64], arena);
+            assert_eq!(arena.chunk_count(), 2);
+
+            assert!(arena.alloc_ref(1u32).is_err());
+        });
+    }
+
+    #[test]
+    fn get_rejects_an_offset_out_of_bounds_for_the_handed_arena() {
+        // allocate something offset far enough into a big arena...
+        let r: Ref<u32> = Arena::with(8 * KB, |big| {
+            big.alloc_ref([0u8; 4096]).unwrap();
+            big.alloc_ref(0u32).unwrap()
+        });
+
+        // ...then resolve it against an unrelated, much smaller arena. The
+        // offset is still "valid" as a `usize`, but it falls outside this
+        // arena's storage, so `get` must refuse it instead of reading past
+        // the buffer.
+        Arena::with(16, |small| {
+            assert!(r.get(small).is_none());
+        });
+    }
+}
@@ -0,0 +1,107 @@
+//! Overflow- and validity-checked size/alignment arithmetic.
+//!
+//! Modeled after the `Size`/`Align` newtypes in rustc's `rustc_abi` layout
+//! module: wrapping the raw `usize` values keeps invalid alignments
+//! unrepresentable and forces allocation math through `checked_*` APIs
+//! instead of ad-hoc bit tricks that silently wrap on overflow.
+
+/// A validated power-of-two alignment.
+///
+/// Stored as the base-2 exponent rather than the raw byte value, so an
+/// `Align` can never hold a non-power-of-two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Align {
+    pow2: u8,
+}
+
+impl Align {
+    pub const ONE: Align = Align { pow2: 0 };
+
+    /// Validates `bytes` as a power of two and wraps it.
+    pub fn from_bytes(bytes: usize) -> Result<Align, AlignError> {
+        if bytes == 0 || !bytes.is_power_of_two() {
+            return Err(AlignError::NotPowerOfTwo(bytes));
+        }
+
+        Ok(Align { pow2: bytes.trailing_zeros() as u8 })
+    }
+
+    pub fn bytes(self) -> usize {
+        1usize << self.pow2
+    }
+
+    fn mask(self) -> usize {
+        self.bytes() - 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignError {
+    /// `0` or not a power of two.
+    NotPowerOfTwo(usize),
+}
+
+/// A byte count, with overflow-checked arithmetic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Size {
+    raw: usize,
+}
+
+impl Size {
+    pub const ZERO: Size = Size { raw: 0 };
+
+    pub fn from_bytes(bytes: usize) -> Size {
+        Size { raw: bytes }
+    }
+
+    pub fn bytes(self) -> usize {
+        self.raw
+    }
+
+    pub fn checked_add(self, other: Size) -> Option<Size> {
+        self.raw.checked_add(other.raw).map(Size::from_bytes)
+    }
+
+    pub fn checked_sub(self, other: Size) -> Option<Size> {
+        self.raw.checked_sub(other.raw).map(Size::from_bytes)
+    }
+
+    /// Rounds up to the next multiple of `align`, or `None` on overflow.
+    pub fn align_to(self, align: Align) -> Option<Size> {
+        let mask = align.mask();
+        self.raw.checked_add(mask).map(|v| Size::from_bytes(v & !mask))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_rejects_non_power_of_two() {
+        assert!(Align::from_bytes(0).is_err());
+        assert!(Align::from_bytes(3).is_err());
+        assert!(Align::from_bytes(1).is_ok());
+        assert!(Align::from_bytes(1024).is_ok());
+    }
+
+    #[test]
+    fn align_to_rounds_up() {
+        let align = Align::from_bytes(16).unwrap();
+        assert_eq!(Size::from_bytes(0).align_to(align), Some(Size::from_bytes(0)));
+        assert_eq!(Size::from_bytes(1).align_to(align), Some(Size::from_bytes(16)));
+        assert_eq!(Size::from_bytes(16).align_to(align), Some(Size::from_bytes(16)));
+        assert_eq!(Size::from_bytes(17).align_to(align), Some(Size::from_bytes(32)));
+    }
+
+    #[test]
+    fn align_to_reports_overflow() {
+        let align = Align::from_bytes(1024).unwrap();
+        assert_eq!(Size::from_bytes(usize::MAX).align_to(align), None);
+    }
+
+    #[test]
+    fn checked_add_reports_overflow() {
+        assert_eq!(Size::from_bytes(usize::MAX).checked_add(Size::from_bytes(1)), None);
+    }
+}
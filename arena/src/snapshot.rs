@@ -0,0 +1,242 @@
+//! Persisting an [`Arena`] to disk and reloading it.
+//!
+//! Modeled on the `odht` on-disk layout: a small fixed header followed
+//! directly by the live bytes, with [`Header::sanity_check`] validating
+//! the tag, format version, and trailing byte count before anything else
+//! trusts the file.
+//!
+//! Reloading puts the bytes at a new address, so raw pointers (and
+//! anything allocated through `Box::new_in`) handed out before the save
+//! are meaningless afterwards. Use [`Ref`](crate::Ref) for anything that
+//! needs to survive a round trip.
+//!
+//! Only an arena that hasn't grown past its first chunk can be saved:
+//! `reserve` enough space up front if you intend to snapshot it.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::mem::size_of;
+use std::path::Path;
+
+use crate::{Arena, Pod};
+
+const MAGIC: [u8; 4] = *b"ARNA";
+const FILE_FORMAT_VERSION: [u8; 4] = [1, 0, 0, 0];
+
+/// On-disk header preceding a saved arena's live bytes.
+#[repr(C, align(8))]
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+    pub tag: [u8; 4],
+    pub file_format_version: [u8; 4],
+    pub size_of_header: u8,
+    _reserved: [u8; 7],
+    pub used_bytes: u64,
+    pub capacity: u64,
+}
+
+unsafe impl Pod for Header {}
+
+impl Header {
+    fn new(used_bytes: u64, capacity: u64) -> Header {
+        Header {
+            tag: MAGIC,
+            file_format_version: FILE_FORMAT_VERSION,
+            size_of_header: size_of::<Header>() as u8,
+            _reserved: [0; 7],
+            used_bytes,
+            capacity,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(self as *const Header as *const u8, size_of::<Header>())
+        }
+    }
+
+    /// Validates `self` against the number of trailing bytes actually
+    /// present (`raw_len` includes the header itself).
+    pub fn sanity_check(&self, raw_len: usize) -> Result<(), SnapshotError> {
+        if self.tag != MAGIC {
+            return Err(SnapshotError::BadTag(self.tag));
+        }
+
+        if self.file_format_version != FILE_FORMAT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(self.file_format_version));
+        }
+
+        if self.size_of_header as usize != size_of::<Header>() {
+            return Err(SnapshotError::HeaderSizeMismatch {
+                expected: size_of::<Header>() as u8,
+                got: self.size_of_header,
+            });
+        }
+
+        let expected_len = size_of::<Header>() + self.used_bytes as usize;
+        if raw_len != expected_len {
+            return Err(SnapshotError::LengthMismatch { expected: expected_len, got: raw_len });
+        }
+
+        if self.capacity < self.used_bytes {
+            return Err(SnapshotError::InvalidCapacity {
+                used_bytes: self.used_bytes,
+                capacity: self.capacity,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+
+    /// didn't start with `b"ARNA"`
+    BadTag([u8; 4]),
+
+    /// `file_format_version` doesn't match what this build writes
+    UnsupportedVersion([u8; 4]),
+
+    /// on-disk `size_of_header` doesn't match `size_of::<Header>()`
+    HeaderSizeMismatch { expected: u8, got: u8 },
+
+    /// trailing byte count doesn't match `used_bytes`
+    LengthMismatch { expected: usize, got: usize },
+
+    /// `capacity` is smaller than `used_bytes`
+    InvalidCapacity { used_bytes: u64, capacity: u64 },
+
+    /// the arena grew past its first chunk, so its live bytes aren't one
+    /// contiguous region; `reserve` enough space up front to avoid this
+    MultipleChunks { chunk_count: usize },
+}
+
+impl<'a> Arena<'a> {
+    /// Writes this arena's header and live bytes to `path`.
+    ///
+    /// Fails with [`SnapshotError::MultipleChunks`] if the arena has grown
+    /// past its first chunk.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let data = unsafe { self.get_data() };
+
+        if data.chunk_count() != 1 {
+            return Err(SnapshotError::MultipleChunks { chunk_count: data.chunk_count() });
+        }
+
+        let chunk = &data.chunks[0];
+        let header = Header::new(chunk.offset as u64, chunk.storage.len() as u64);
+
+        let mut file = File::create(path).map_err(SnapshotError::Io)?;
+        file.write_all(header.as_bytes()).map_err(SnapshotError::Io)?;
+        file.write_all(&chunk.storage[..chunk.offset]).map_err(SnapshotError::Io)?;
+        Ok(())
+    }
+
+    /// Reads a file written by [`save`](Arena::save), rebuilds an arena
+    /// from it at whatever address it lands at, and runs `k` against it.
+    ///
+    /// The rebuilt arena's backing storage is sized to exactly the saved
+    /// payload (`used_bytes`), not the header's `capacity`: a saved file
+    /// can only ever claim a `used_bytes` bounded by its own length on
+    /// disk, so this keeps a corrupted or crafted `capacity` from forcing
+    /// an oversized allocation. `reserve` more if you intend to keep
+    /// allocating into the loaded arena.
+    pub fn load<R>(
+        path: impl AsRef<Path>,
+        k: impl for<'b> FnOnce(&Arena<'b>) -> R,
+    ) -> Result<R, SnapshotError> {
+        let mut file = File::open(path).map_err(SnapshotError::Io)?;
+        let mut raw = Vec::new();
+        file.read_to_end(&mut raw).map_err(SnapshotError::Io)?;
+
+        let used = Arena::with(size_of::<Header>(), |scratch| {
+            let header: &Header = scratch.view(&raw).ok_or(SnapshotError::LengthMismatch {
+                expected: size_of::<Header>(),
+                got: raw.len(),
+            })?;
+            header.sanity_check(raw.len())?;
+            Ok::<_, SnapshotError>(header.used_bytes as usize)
+        })?;
+
+        let live = &raw[size_of::<Header>()..];
+
+        Ok(Arena::with(used, |arena| {
+            let data = unsafe { arena.get_data() };
+            data.chunks[0].storage[..used].copy_from_slice(live);
+            data.chunks[0].offset = used;
+            k(arena)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Header, SnapshotError};
+    use crate::{Arena, Ref, KB};
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("arena-snapshot-test-{}.bin", std::process::id()));
+
+        let r: Ref<u32> = Arena::with(KB, |arena| {
+            let r = arena.alloc_ref(0xdead_beefu32).unwrap();
+            arena.save(&path).unwrap();
+            r
+        });
+
+        let value = Arena::load(&path, |arena| *r.get(arena).unwrap()).unwrap();
+        assert_eq!(value, 0xdead_beef);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("arena-snapshot-test-truncated-{}.bin", std::process::id()));
+
+        std::fs::write(&path, b"not an arena").unwrap();
+
+        let result = Arena::load(&path, |_arena| ());
+        assert!(result.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_capacity_smaller_than_used_bytes() {
+        // `raw_len` matches `used_bytes` exactly, so only the capacity
+        // check below should reject this header.
+        let header = Header::new(100, 1);
+        let raw_len = std::mem::size_of::<Header>() + 100;
+
+        assert!(matches!(
+            header.sanity_check(raw_len),
+            Err(SnapshotError::InvalidCapacity { .. })
+        ));
+    }
+
+    #[test]
+    fn load_sizes_the_arena_off_used_bytes_not_the_claimed_capacity() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("arena-snapshot-test-huge-capacity-{}.bin", std::process::id()));
+
+        // a header is internally consistent (`capacity >= used_bytes`) but
+        // can still claim a capacity wildly disproportionate to the tiny
+        // payload actually on disk; loading it must not allocate anywhere
+        // near `capacity`.
+        let payload = b"hi";
+        let header = Header::new(payload.len() as u64, 4 * 1024 * 1024 * 1024);
+        let mut raw = header.as_bytes().to_vec();
+        raw.extend_from_slice(payload);
+        std::fs::write(&path, &raw).unwrap();
+
+        let allocated = Arena::load(&path, |arena| arena.total_allocated()).unwrap();
+        assert_eq!(allocated, payload.len());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}